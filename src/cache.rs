@@ -0,0 +1,174 @@
+/* A small TTL-aware answer cache, so repeated queries (or a batch run)
+ * don't have to re-hit the resolver every time. */
+
+use std::collections::{HashMap, VecDeque};
+use std::time::{Duration, Instant};
+
+use crate::packet::DnsRecord;
+use crate::{DnsClass, DnsType};
+
+#[derive(Clone, PartialEq, Eq, Hash)]
+struct CacheKey {
+    name: String,
+    qtype: u16,
+    qclass: u8,
+}
+
+impl CacheKey {
+    fn new(name: &str, qtype: &DnsType, qclass: &DnsClass) -> CacheKey {
+        CacheKey { name: name.to_ascii_lowercase(), qtype: qtype.to_u16(), qclass: qclass.to_u8() }
+    }
+}
+
+struct CachedAnswer {
+    /* The full answer RRset, including any covering RRSIG records, so a
+     * DNSSEC response is still verifiable after a cache hit. */
+    records: Vec<DnsRecord>,
+    expires_at: Instant,
+}
+
+pub(crate) struct ResponseCache {
+    capacity: usize,
+    entries: HashMap<CacheKey, CachedAnswer>,
+    /* Least-recently-used key at the front, most-recently-used at the back. */
+    recency: VecDeque<CacheKey>,
+}
+
+impl ResponseCache {
+    pub(crate) fn new(capacity: usize) -> ResponseCache {
+        ResponseCache { capacity, entries: HashMap::new(), recency: VecDeque::new() }
+    }
+
+    pub(crate) fn get(&mut self, name: &str, qtype: &DnsType, qclass: &DnsClass) -> Option<Vec<DnsRecord>> {
+        let key = CacheKey::new(name, qtype, qclass);
+
+        match self.entries.get(&key) {
+            Some(answer) if Instant::now() < answer.expires_at => {
+                self.touch(&key);
+                Some(self.entries[&key].records.clone())
+            }
+            Some(_) => {
+                self.entries.remove(&key);
+                self.recency.retain(|k| k != &key);
+                None
+            }
+            None => None,
+        }
+    }
+
+    pub(crate) fn insert(&mut self, name: &str, qtype: &DnsType, qclass: &DnsClass, records: Vec<DnsRecord>) {
+        if self.capacity == 0 || records.is_empty() {
+            return;
+        }
+
+        let min_ttl = records.iter().map(|r| r.ttl).min().unwrap_or(0);
+        let key = CacheKey::new(name, qtype, qclass);
+
+        if !self.entries.contains_key(&key) && self.entries.len() >= self.capacity {
+            if let Some(lru) = self.recency.pop_front() {
+                self.entries.remove(&lru);
+            }
+        }
+
+        self.entries.insert(
+            key.clone(),
+            CachedAnswer { records, expires_at: Instant::now() + Duration::from_secs(min_ttl as u64) },
+        );
+        self.touch(&key);
+    }
+
+    fn touch(&mut self, key: &CacheKey) {
+        self.recency.retain(|k| k != key);
+        self.recency.push_back(key.clone());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::packet::RData;
+    use std::net::Ipv4Addr;
+
+    fn a_record(name: &str, ttl: u32) -> DnsRecord {
+        DnsRecord {
+            name: name.to_string(),
+            rtype: DnsType::Address,
+            rclass: DnsClass::Internet,
+            ttl,
+            rdata: RData::A(Ipv4Addr::new(127, 0, 0, 1)),
+        }
+    }
+
+    #[test]
+    fn miss_on_an_empty_cache() {
+        let mut cache = ResponseCache::new(4);
+        assert!(cache.get("example.com", &DnsType::Address, &DnsClass::Internet).is_none());
+    }
+
+    #[test]
+    fn insert_then_get_round_trips_the_records() {
+        let mut cache = ResponseCache::new(4);
+        cache.insert("example.com", &DnsType::Address, &DnsClass::Internet, vec![a_record("example.com", 300)]);
+
+        let hit = cache.get("example.com", &DnsType::Address, &DnsClass::Internet);
+        assert_eq!(hit.map(|r| r.len()), Some(1));
+    }
+
+    #[test]
+    fn lookup_is_case_insensitive_and_keyed_by_type_and_class() {
+        let mut cache = ResponseCache::new(4);
+        cache.insert("Example.COM", &DnsType::Address, &DnsClass::Internet, vec![a_record("example.com", 300)]);
+
+        assert!(cache.get("example.com", &DnsType::Address, &DnsClass::Internet).is_some());
+        assert!(cache.get("example.com", &DnsType::CName, &DnsClass::Internet).is_none());
+    }
+
+    #[test]
+    fn zero_capacity_disables_caching() {
+        let mut cache = ResponseCache::new(0);
+        cache.insert("example.com", &DnsType::Address, &DnsClass::Internet, vec![a_record("example.com", 300)]);
+        assert!(cache.get("example.com", &DnsType::Address, &DnsClass::Internet).is_none());
+    }
+
+    #[test]
+    fn empty_answer_is_not_cached() {
+        let mut cache = ResponseCache::new(4);
+        cache.insert("example.com", &DnsType::Address, &DnsClass::Internet, vec![]);
+        assert!(cache.get("example.com", &DnsType::Address, &DnsClass::Internet).is_none());
+    }
+
+    #[test]
+    fn an_expired_entry_is_evicted_on_lookup() {
+        let mut cache = ResponseCache::new(4);
+        cache.insert("example.com", &DnsType::Address, &DnsClass::Internet, vec![a_record("example.com", 0)]);
+        assert!(cache.get("example.com", &DnsType::Address, &DnsClass::Internet).is_none());
+    }
+
+    #[test]
+    fn evicts_the_least_recently_used_entry_when_over_capacity() {
+        let mut cache = ResponseCache::new(2);
+        cache.insert("a.com", &DnsType::Address, &DnsClass::Internet, vec![a_record("a.com", 300)]);
+        cache.insert("b.com", &DnsType::Address, &DnsClass::Internet, vec![a_record("b.com", 300)]);
+        cache.insert("c.com", &DnsType::Address, &DnsClass::Internet, vec![a_record("c.com", 300)]);
+
+        assert!(cache.get("a.com", &DnsType::Address, &DnsClass::Internet).is_none());
+        assert!(cache.get("b.com", &DnsType::Address, &DnsClass::Internet).is_some());
+        assert!(cache.get("c.com", &DnsType::Address, &DnsClass::Internet).is_some());
+    }
+
+    #[test]
+    fn a_get_refreshes_recency_so_it_survives_the_next_eviction() {
+        let mut cache = ResponseCache::new(2);
+        cache.insert("a.com", &DnsType::Address, &DnsClass::Internet, vec![a_record("a.com", 300)]);
+        cache.insert("b.com", &DnsType::Address, &DnsClass::Internet, vec![a_record("b.com", 300)]);
+
+        // Touch `a.com` so `b.com` becomes the least-recently-used entry.
+        assert!(cache.get("a.com", &DnsType::Address, &DnsClass::Internet).is_some());
+
+        cache.insert("c.com", &DnsType::Address, &DnsClass::Internet, vec![a_record("c.com", 300)]);
+
+        assert!(cache.get("a.com", &DnsType::Address, &DnsClass::Internet).is_some());
+        assert!(cache.get("b.com", &DnsType::Address, &DnsClass::Internet).is_none());
+        assert!(cache.get("c.com", &DnsType::Address, &DnsClass::Internet).is_some());
+    }
+}
@@ -1,7 +1,12 @@
 #![allow(unused)] // TODO
 
+mod cache;
+mod packet;
+mod resolv;
+mod transport;
+
 use clap::Parser;
-use std::{net::Ipv4Addr, net::UdpSocket, str::FromStr};
+use std::{net::Ipv4Addr, str::FromStr, time::Duration};
 use socket::{htons,htonl,ntohs,ntohl};
 
 /// A DNS client
@@ -14,84 +19,91 @@ struct DiggerArguments
     /// UDP port to use to send request
     #[clap(short, long, default_value_t = 53)]
     port: u16,
-    /// CNAME to query
+    /// Force TCP from the start instead of only falling back to it on a truncated UDP reply
+    #[clap(long)]
+    tcp: bool,
+    /// Query type (A, AAAA, MX, NS, TXT, SOA, CNAME, ANY, ...)
+    #[clap(short = 't', long = "type", default_value = "A")]
+    qtype: DnsType,
+    /// Query class
+    #[clap(short = 'c', long = "class", default_value = "IN")]
+    qclass: DnsClass,
+    /// Attach an EDNS0 OPT pseudo-record to the query
+    #[clap(long)]
+    edns: bool,
+    /// Request DNSSEC data (sets the DO bit); implies --edns
+    #[clap(long)]
+    dnssec: bool,
+    /// Maximum number of answers to keep in the in-process response cache (0 disables caching)
+    #[clap(long, default_value_t = 128)]
+    cache_size: usize,
+    /// One or more CNAMEs to query, in order; the response cache is shared across all of them
     #[arg(required = true)]
-    cname: String,
+    cnames: Vec<String>,
 }
 
 struct DiggerSettings {
-    resolver: Ipv4Addr,
+    /* Ordered list of resolvers to try in sequence on failure/timeout. */
+    resolvers: Vec<Ipv4Addr>,
     port: u16,
-    cname: String,
+    cnames: Vec<String>,
+    qtype: DnsType,
+    qclass: DnsClass,
+    resolv: resolv::ResolvConf,
+    force_tcp: bool,
+    edns: bool,
+    dnssec: bool,
+    cache_size: usize,
 }
 
 impl DiggerSettings {
     fn dump(&self)
     {
+        let resolvers : Vec<String> = self.resolvers.iter().map(Ipv4Addr::to_string).collect();
         println!("Configuration:");
-        println!("    Resolver: {}", self.resolver);
-        println!("    Port    : {}", self.port);
-        println!("    CNAME   : {}", self.cname);
-    }
-}
-
-#[derive(Debug)]
-enum DiggerError
-{
-    /* Could not find system default resolver */
-    ResolverNotFound,
-}
-
-impl DiggerError {
-    fn to_str(&self) -> &str {
-        match self {
-            DiggerError::ResolverNotFound => {
-                "Could not determine system resolver."
-            }
+        println!("    Resolvers: {}", resolvers.join(", "));
+        println!("    Port     : {}", self.port);
+        println!("    CNAMEs   : {}", self.cnames.join(", "));
+        println!("    Type     : {}", self.qtype.dig_name());
+        println!("    Class    : {}", self.qclass.dig_name());
+        if !self.resolv.search.is_empty() {
+            println!("    Search   : {}", self.resolv.search.join(", "));
+        }
+        println!("    Transport: {}", if self.force_tcp { "TCP" } else { "UDP" });
+        if self.edns {
+            println!("    EDNS0    : yes (DO={})", self.dnssec);
         }
     }
 }
 
 const RESOLV_CONF : &str = "/etc/resolv.conf";
 
-fn get_system_resolver() -> Result<Ipv4Addr, DiggerError>
-{
-    /* https://doc.rust-lang.org/stable/rust-by-example/std_misc/file/read_lines.html */
-    let lines : Vec<String> = std::fs::read_to_string(RESOLV_CONF)
-        .unwrap()
-        .lines()
-        .map(String::from)
-        .collect();
-
-    /* Find the first `nameserver` line */
-    for line in lines {
-        if line.starts_with("nameserver") {
-            match line.split(' ').last() {
-                Some(s) => { return Ok(Ipv4Addr::from_str(s).unwrap()) },
-                None => { continue }
-            };
-        }
-    }
-
-    Err(DiggerError::ResolverNotFound)
-}
+/* Advertised in the EDNS0 OPT record as the UDP payload size we can accept. */
+const EDNS_UDP_PAYLOAD_SIZE : u16 = 4096;
 
 /* Make sure our arguments are in a sane state */
 fn sanitize_arguments(args: DiggerArguments) -> DiggerSettings {
+    let resolv = resolv::ResolvConf::load(RESOLV_CONF);
 
-    /* Resolver is optional. If it does not exist, we use the system's default one. */
-    let resolver = match args.resolver {
-        Some(r) => r,
-        None => match get_system_resolver() {
-            Ok(t) => t,
-            Err(e) => {
-                println!("{}", e.to_str());
-                std::process::exit(e as i32);
-            }
-        }
+    /* Resolver is optional. If it does not exist, we fall back to the
+     * system's configured nameservers, tried in order. */
+    let resolvers = match args.resolver {
+        Some(r) => vec![r],
+        None => resolv.nameservers.clone(),
     };
 
-    DiggerSettings{ resolver, port: args.port, cname: args.cname }
+    DiggerSettings{
+        resolvers,
+        port: args.port,
+        cnames: args.cnames,
+        qtype: args.qtype,
+        qclass: args.qclass,
+        resolv,
+        force_tcp: args.tcp,
+        edns: args.edns || args.dnssec,
+        dnssec: args.dnssec,
+        cache_size: args.cache_size,
+    }
 }
 
 fn banner()
@@ -128,13 +140,13 @@ fn banner()
 *   |                    ARCOUNT                    |
 *   +--+--+--+--+--+--+--+--+--+--+--+--+--+--+--+--+
 */
-struct DnsHeader {
-    id : u16,
-    cfg : u16,
-    qdcount : u16,
-    ancount : u16,
-    nscount : u16,
-    arcount : u16,
+pub(crate) struct DnsHeader {
+    pub(crate) id : u16,
+    pub(crate) cfg : u16,
+    pub(crate) qdcount : u16,
+    pub(crate) ancount : u16,
+    pub(crate) nscount : u16,
+    pub(crate) arcount : u16,
 }
 
 enum DnsOperations {
@@ -143,7 +155,8 @@ enum DnsOperations {
     Status,
 }
 
-enum DnsType {
+#[derive(Clone)]
+pub(crate) enum DnsType {
     Address,
     NameServer,
     MailDestination,
@@ -160,14 +173,22 @@ enum DnsType {
     MailboxInformation,
     MailExchange,
     Text,
+    Aaaa,
+    Opt,
+    Rrsig,
+    Dnskey,
     AXFR,
     MAILB,
     MAILA,
     All,
+    /* A numeric RR type we don't otherwise recognize. Kept as a full u16
+     * (not a u8) since RTYPE/QTYPE is a 16-bit field on the wire - e.g. a
+     * CAA record (257) must not alias onto A (1) by truncation. */
+    Unknown(u16),
 }
 
 impl DnsType {
-    fn to_u8(&self) -> u8 {
+    pub(crate) fn to_u16(&self) -> u16 {
         match self {
             Self::Address => 1,
             Self::NameServer => 2,
@@ -185,14 +206,21 @@ impl DnsType {
             Self::MailboxInformation => 14,
             Self::MailExchange => 15,
             Self::Text => 16,
+            Self::Aaaa => 28,
+            Self::Opt => 41,
+            Self::Rrsig => 46,
+            Self::Dnskey => 48,
             Self::AXFR => 252,
             Self::MAILA => 253,
             Self::MAILB => 254,
             Self::All => 255,
+            Self::Unknown(v) => *v,
         }
     }
 
-    fn from_u8(v: u8) -> DnsType {
+    /* This never aborts the process: an unrecognized RR type in a
+     * response is not fatal, so it's kept around as `Unknown` instead. */
+    pub(crate) fn from_u16(v: u16) -> DnsType {
         match v {
             1 => Self::Address,
             2 => Self::NameServer,
@@ -210,49 +238,154 @@ impl DnsType {
             14 => Self::MailboxInformation,
             15 => Self::MailExchange,
             16 => Self::Text,
+            28 => Self::Aaaa,
+            41 => Self::Opt,
+            46 => Self::Rrsig,
+            48 => Self::Dnskey,
             252 => Self::AXFR,
             253 => Self::MAILA,
             254 => Self::MAILB,
             255 => Self::All,
-            _ => {
-                println!("E: unknown DNS type: {}", v);
-                std::process::exit(1);
-            }
+            other => Self::Unknown(other),
         }
     }
+
+    /* Mnemonic used by `dig` and friends, e.g. "A", "CNAME", "MX". An
+     * unrecognized type is rendered as "TYPEnnn", again matching `dig`. */
+    pub(crate) fn dig_name(&self) -> String {
+        match self {
+            Self::Address => "A".to_string(),
+            Self::NameServer => "NS".to_string(),
+            Self::MailDestination => "MD".to_string(),
+            Self::MailForwarder => "MF".to_string(),
+            Self::CName => "CNAME".to_string(),
+            Self::StartOfAuthority => "SOA".to_string(),
+            Self::MailBox => "MB".to_string(),
+            Self::MailGroup => "MG".to_string(),
+            Self::MailRename => "MR".to_string(),
+            Self::Null => "NULL".to_string(),
+            Self::WellKnownService => "WKS".to_string(),
+            Self::Pointer => "PTR".to_string(),
+            Self::HostInformation => "HINFO".to_string(),
+            Self::MailboxInformation => "MINFO".to_string(),
+            Self::MailExchange => "MX".to_string(),
+            Self::Text => "TXT".to_string(),
+            Self::Aaaa => "AAAA".to_string(),
+            Self::Opt => "OPT".to_string(),
+            Self::Rrsig => "RRSIG".to_string(),
+            Self::Dnskey => "DNSKEY".to_string(),
+            Self::AXFR => "AXFR".to_string(),
+            Self::MAILA => "MAILA".to_string(),
+            Self::MAILB => "MAILB".to_string(),
+            Self::All => "ANY".to_string(),
+            Self::Unknown(v) => format!("TYPE{}", v),
+        }
+    }
+
+    /* Parses the mnemonics accepted on the command line (`-t`). */
+    fn from_name(s: &str) -> Result<DnsType, String> {
+        match s.to_ascii_uppercase().as_str() {
+            "A" => Ok(Self::Address),
+            "NS" => Ok(Self::NameServer),
+            "MD" => Ok(Self::MailDestination),
+            "MF" => Ok(Self::MailForwarder),
+            "CNAME" => Ok(Self::CName),
+            "SOA" => Ok(Self::StartOfAuthority),
+            "MB" => Ok(Self::MailBox),
+            "MG" => Ok(Self::MailGroup),
+            "MR" => Ok(Self::MailRename),
+            "NULL" => Ok(Self::Null),
+            "WKS" => Ok(Self::WellKnownService),
+            "PTR" => Ok(Self::Pointer),
+            "HINFO" => Ok(Self::HostInformation),
+            "MINFO" => Ok(Self::MailboxInformation),
+            "MX" => Ok(Self::MailExchange),
+            "TXT" => Ok(Self::Text),
+            "AAAA" => Ok(Self::Aaaa),
+            "OPT" => Ok(Self::Opt),
+            "RRSIG" => Ok(Self::Rrsig),
+            "DNSKEY" => Ok(Self::Dnskey),
+            "AXFR" => Ok(Self::AXFR),
+            "MAILA" => Ok(Self::MAILA),
+            "MAILB" => Ok(Self::MAILB),
+            "ANY" => Ok(Self::All),
+            _ => Err(format!("unknown DNS type: {}", s)),
+        }
+    }
+}
+
+impl FromStr for DnsType {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Self::from_name(s)
+    }
 }
 
-enum DnsClass {
+#[derive(Clone)]
+pub(crate) enum DnsClass {
     Internet,
     CSNET,
     CHAOS,
     Hesiod,
+    /* A numeric class we don't otherwise recognize. */
+    Unknown(u8),
 }
 
 impl DnsClass {
-    fn to_u8(&self) -> u8 {
+    pub(crate) fn to_u8(&self) -> u8 {
         match self {
             Self::Internet => 1,
             Self::CSNET => 2,
             Self::CHAOS => 3,
             Self::Hesiod => 4,
+            Self::Unknown(v) => *v,
+        }
+    }
+
+    pub(crate) fn dig_name(&self) -> String {
+        match self {
+            Self::Internet => "IN".to_string(),
+            Self::CSNET => "CS".to_string(),
+            Self::CHAOS => "CH".to_string(),
+            Self::Hesiod => "HS".to_string(),
+            Self::Unknown(v) => format!("CLASS{}", v),
         }
     }
 
-    fn from_u8(v: u8) -> DnsClass {
+    /* Unlike the class byte this once was, an unrecognized value in a
+     * response is not fatal: it's kept around as `Unknown`, matching how
+     * `DnsType::from_u16` treats an unrecognized RR type. */
+    pub(crate) fn from_u8(v: u8) -> DnsClass {
         match v {
             1 => Self::Internet,
             2 => Self::CSNET,
             3 => Self::CHAOS,
             4 => Self::Hesiod,
-            _ => {
-                println!("E: unknown DNS class {}", v);
-                std::process::exit(1);
-            }
+            other => Self::Unknown(other),
+        }
+    }
+
+    /* Parses the mnemonics accepted on the command line (`-c`). */
+    fn from_name(s: &str) -> Result<DnsClass, String> {
+        match s.to_ascii_uppercase().as_str() {
+            "IN" => Ok(Self::Internet),
+            "CS" => Ok(Self::CSNET),
+            "CH" => Ok(Self::CHAOS),
+            "HS" => Ok(Self::Hesiod),
+            _ => Err(format!("unknown DNS class: {}", s)),
         }
     }
 }
 
+impl FromStr for DnsClass {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Self::from_name(s)
+    }
+}
+
 impl DnsHeader {
     fn new() -> DnsHeader {
         DnsHeader { id: 0, cfg: 0, qdcount: 0, ancount: 0, nscount: 0, arcount: 0 }
@@ -318,25 +451,137 @@ impl DnsHeader {
     fn ra(&self) -> bool { self.get_cfg_bit(8) }
     fn set_ra(& mut self, set: bool) { self.set_cfg_bit(8, set); }
 
-    fn rcode(&self) -> u8 {
+    pub(crate) fn rcode(&self) -> u8 {
         ntohs((self.cfg & 0xF000) >> 12) as u8
     }
 
+    /* Mnemonic used by `dig` and friends, e.g. "NOERROR", "NXDOMAIN". An
+     * unrecognized code is rendered as "RCODEnnn". */
+    pub(crate) fn rcode_name(&self) -> String {
+        match self.rcode() {
+            0 => "NOERROR".to_string(),
+            1 => "FORMERR".to_string(),
+            2 => "SERVFAIL".to_string(),
+            3 => "NXDOMAIN".to_string(),
+            4 => "NOTIMP".to_string(),
+            5 => "REFUSED".to_string(),
+            other => format!("RCODE{}", other),
+        }
+    }
+
     fn set_rcode(& mut self, rc: u8) {
         self.cfg &= 0x0FFF;
         self.cfg |= htons(rc as u16) << 12;
     }
 }
 
-fn build_dns_request(resolver: Ipv4Addr, port: u16, cname: String)
-{
+fn build_dns_request(cname: String, qtype: DnsType, qclass: DnsClass, edns: bool, dnssec: bool) -> packet::DnsMessage {
     let mut header = DnsHeader::new();
     header.id = 1;
     header.set_opcode(DnsOperations::Query);
+    header.set_rd(true);
     header.qdcount = 1;
+
+    let question = packet::DnsQuestion::new(cname, qtype, qclass);
+
+    let mut message = packet::DnsMessage::new(header);
+    message.questions.push(question);
+
+    if edns {
+        message.edns = Some(packet::EdnsOpt::new(EDNS_UDP_PAYLOAD_SIZE, dnssec));
+    }
+
+    message
 }
 
-fn main() -> Result<(), DiggerError> {
+/* Resolves a single CNAME (through its search-list candidates) and prints
+ * the result, consulting and populating `cache` along the way. Called once
+ * per name in `parameters.cnames`, so a name repeated later in the same
+ * batch - or sharing a qualified candidate with an earlier one - is served
+ * from `cache` instead of re-hitting the resolver. */
+fn resolve_and_print(
+    cname: &str,
+    parameters: &DiggerSettings,
+    cache: &mut cache::ResponseCache,
+    timeout: Duration,
+    initial_transport: &transport::Transport,
+) {
+    let candidates = parameters.resolv.qualify(cname);
+    let mut response = None;
+    let mut cached = None;
+
+    'candidates: for name in &candidates {
+        if let Some(records) = cache.get(name, &parameters.qtype, &parameters.qclass) {
+            cached = Some((name.clone(), records));
+            break 'candidates;
+        }
+
+        let request = build_dns_request(
+            name.clone(),
+            parameters.qtype.clone(),
+            parameters.qclass.clone(),
+            parameters.edns,
+            parameters.dnssec,
+        ).to_bytes();
+
+        for resolver in &parameters.resolvers {
+            for _ in 0..parameters.resolv.attempts {
+                let reply = match initial_transport.send_query(*resolver, parameters.port, timeout, &request) {
+                    Ok(bytes) => bytes,
+                    Err(_) => continue,
+                };
+
+                let message = match packet::DnsMessage::from_bytes(reply) {
+                    Ok(message) => message,
+                    Err(e) => {
+                        println!("W: ignoring malformed reply from {}: {}", resolver, e);
+                        continue;
+                    }
+                };
+
+                /* A truncated UDP reply must be re-issued over TCP to get
+                 * the full answer. */
+                let message = if !parameters.force_tcp && message.header.tc() {
+                    match transport::Transport::Tcp.send_query(*resolver, parameters.port, timeout, &request) {
+                        Ok(bytes) => packet::DnsMessage::from_bytes(bytes).unwrap_or(message),
+                        Err(_) => message,
+                    }
+                } else {
+                    message
+                };
+
+                let noerror = message.header.rcode() == 0;
+                response = Some((name.clone(), message));
+
+                if noerror {
+                    break 'candidates;
+                }
+
+                /* A non-NOERROR status (e.g. NXDOMAIN) for this qualified
+                 * candidate doesn't mean the bare name doesn't exist -
+                 * keep working through the rest of the search list before
+                 * giving up. */
+                continue 'candidates;
+            }
+        }
+    }
+
+    if let Some((name, records)) = cached {
+        println!(";; (cached answer for {})", name);
+        packet::DnsMessage::print_section("ANSWER", &records);
+        return;
+    }
+
+    match response {
+        Some((name, message)) => {
+            message.print();
+            cache.insert(&name, &parameters.qtype, &parameters.qclass, message.answers);
+        }
+        None => println!("E: no response from any configured resolver"),
+    }
+}
+
+fn main() {
     banner();
 
     let args = DiggerArguments::parse();
@@ -344,13 +589,54 @@ fn main() -> Result<(), DiggerError> {
 
     parameters.dump();
 
-    let socket = UdpSocket::bind("127.0.0.1:0").expect("Cannot bind to UDP port");
-    println!("Opened socket at {}", socket.local_addr()
-                                        .expect("Could not get socket address"));
+    let timeout = Duration::from_secs(parameters.resolv.timeout as u64);
+    let initial_transport = if parameters.force_tcp { transport::Transport::Tcp } else { transport::Transport::Udp };
+
+    let mut cache = cache::ResponseCache::new(parameters.cache_size);
+
+    for cname in &parameters.cnames {
+        println!(";; Query: {}", cname);
+        resolve_and_print(cname, &parameters, &mut cache, timeout, &initial_transport);
+        println!();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
 
-    build_dns_request(parameters.resolver, parameters.port, parameters.cname);
-    // build DNS packet in a buffer
-    // socket.send_to(buffer, SockAddr(resolver, port))
+    #[test]
+    fn dns_type_from_name_accepts_known_mnemonics_case_insensitively() {
+        assert!(matches!(DnsType::from_name("a").unwrap(), DnsType::Address));
+        assert!(matches!(DnsType::from_name("AAAA").unwrap(), DnsType::Aaaa));
+        assert!(matches!(DnsType::from_name("CnAmE").unwrap(), DnsType::CName));
+        assert!(matches!(DnsType::from_name("dnskey").unwrap(), DnsType::Dnskey));
+    }
+
+    #[test]
+    fn dns_type_from_name_rejects_an_unknown_mnemonic() {
+        assert!(DnsType::from_name("NOSUCHTYPE").is_err());
+    }
+
+    #[test]
+    fn dns_class_from_name_accepts_known_mnemonics_case_insensitively() {
+        assert!(matches!(DnsClass::from_name("in").unwrap(), DnsClass::Internet));
+        assert!(matches!(DnsClass::from_name("CH").unwrap(), DnsClass::CHAOS));
+    }
 
-    Ok(())
+    #[test]
+    fn dns_class_from_name_rejects_an_unknown_mnemonic() {
+        assert!(DnsClass::from_name("NOSUCHCLASS").is_err());
+    }
+
+    #[test]
+    fn dns_class_from_u8_is_not_fatal_on_an_unrecognized_class() {
+        assert!(matches!(DnsClass::from_u8(200), DnsClass::Unknown(200)));
+    }
+
+    #[test]
+    fn dns_type_from_u16_carries_a_type_code_above_255_without_truncating() {
+        // CAA (257) must not alias onto A (1) by truncating to u8.
+        assert!(matches!(DnsType::from_u16(257), DnsType::Unknown(257)));
+    }
 }
@@ -0,0 +1,674 @@
+/* Wire-format encoding/decoding for DNS messages, see https://www.rfc-editor.org/rfc/rfc1035 */
+
+use std::net::{Ipv4Addr, Ipv6Addr};
+
+use socket::{htonl, htons, ntohl, ntohs};
+
+use crate::{DnsClass, DnsHeader, DnsType};
+
+/* RFC 1035 doesn't bound the number of compression pointers a name may
+ * follow, so cap it to something generous but finite to avoid looping
+ * forever on a malicious or corrupt packet. */
+const MAX_NAME_JUMPS: usize = 5;
+
+pub(crate) struct PacketBuffer {
+    buf: Vec<u8>,
+    pos: usize,
+}
+
+impl PacketBuffer {
+    fn new() -> PacketBuffer {
+        PacketBuffer { buf: Vec::new(), pos: 0 }
+    }
+
+    fn from_bytes(buf: Vec<u8>) -> PacketBuffer {
+        PacketBuffer { buf, pos: 0 }
+    }
+
+    fn write_u8(&mut self, val: u8) {
+        self.buf.push(val);
+    }
+
+    fn write_u16(&mut self, val: u16) {
+        self.buf.extend_from_slice(&htons(val).to_ne_bytes());
+    }
+
+    fn write_u32(&mut self, val: u32) {
+        self.buf.extend_from_slice(&htonl(val).to_ne_bytes());
+    }
+
+    /* Splits `name` on '.' into length-prefixed labels, terminated by a
+     * zero-length label. We never emit a compression pointer ourselves -
+     * only the decoder needs to understand them. */
+    fn write_qname(&mut self, name: &str) {
+        for label in name.split('.') {
+            if label.is_empty() {
+                continue;
+            }
+            assert!(label.len() <= 63, "DNS label too long: {}", label);
+            self.write_u8(label.len() as u8);
+            self.buf.extend_from_slice(label.as_bytes());
+        }
+        self.write_u8(0);
+    }
+
+    /* These bytes come straight off the wire (a UDP datagram is trivially
+     * spoofable), so every read is bounds-checked and reports a decode
+     * error instead of indexing out of bounds on a truncated or malicious
+     * packet. */
+    fn read_u8(&mut self) -> Result<u8, String> {
+        let val = *self.buf.get(self.pos).ok_or("unexpected end of packet while reading a byte")?;
+        self.pos += 1;
+        Ok(val)
+    }
+
+    fn peek_u8(&self, offset: usize) -> Result<u8, String> {
+        self.buf.get(offset).copied().ok_or_else(|| "name offset points past the end of the packet".to_string())
+    }
+
+    fn read_u16(&mut self) -> Result<u16, String> {
+        if self.pos + 2 > self.buf.len() {
+            return Err("unexpected end of packet while reading a 16-bit field".to_string());
+        }
+        let raw = u16::from_ne_bytes([self.buf[self.pos], self.buf[self.pos + 1]]);
+        self.pos += 2;
+        Ok(ntohs(raw))
+    }
+
+    fn read_u32(&mut self) -> Result<u32, String> {
+        if self.pos + 4 > self.buf.len() {
+            return Err("unexpected end of packet while reading a 32-bit field".to_string());
+        }
+        let raw = u32::from_ne_bytes([
+            self.buf[self.pos],
+            self.buf[self.pos + 1],
+            self.buf[self.pos + 2],
+            self.buf[self.pos + 3],
+        ]);
+        self.pos += 4;
+        Ok(ntohl(raw))
+    }
+
+    fn read_bytes(&mut self, len: usize) -> Result<Vec<u8>, String> {
+        let end = self.pos.checked_add(len).ok_or("record length overflowed while reading raw bytes")?;
+        if end > self.buf.len() {
+            return Err("unexpected end of packet while reading raw bytes".to_string());
+        }
+        let bytes = self.buf[self.pos..end].to_vec();
+        self.pos = end;
+        Ok(bytes)
+    }
+
+    fn seek(&mut self, pos: usize) {
+        self.pos = pos;
+    }
+
+    /* Reads a (possibly compressed) domain name starting at the current
+     * position. A length byte whose top two bits are both set is not a
+     * label length but a pointer: the remaining 14 bits, combined with the
+     * following byte, give an absolute offset to jump to and continue
+     * reading from. */
+    fn read_name(&mut self) -> Result<String, String> {
+        let mut pos = self.pos;
+        let mut jumped = false;
+        let mut jumps = 0;
+        let mut labels: Vec<String> = Vec::new();
+
+        loop {
+            let len = self.peek_u8(pos)?;
+
+            if len & 0xC0 == 0xC0 {
+                if jumps >= MAX_NAME_JUMPS {
+                    return Err("too many compression pointer jumps while reading a name".to_string());
+                }
+
+                if !jumped {
+                    self.pos = pos + 2;
+                    jumped = true;
+                }
+
+                let b2 = self.peek_u8(pos + 1)? as u16;
+                let offset = (((len as u16) & 0x3F) << 8) | b2;
+                pos = offset as usize;
+                jumps += 1;
+                continue;
+            }
+
+            pos += 1;
+            if len == 0 {
+                break;
+            }
+
+            let end = pos.checked_add(len as usize).ok_or("label length overflowed while reading a name")?;
+            if end > self.buf.len() {
+                return Err("label extends past the end of the packet".to_string());
+            }
+            labels.push(String::from_utf8_lossy(&self.buf[pos..end]).to_string());
+            pos = end;
+        }
+
+        if !jumped {
+            self.pos = pos;
+        }
+
+        Ok(labels.join("."))
+    }
+}
+
+pub(crate) struct DnsQuestion {
+    pub(crate) qname: String,
+    pub(crate) qtype: DnsType,
+    pub(crate) qclass: DnsClass,
+}
+
+impl DnsQuestion {
+    pub(crate) fn new(qname: String, qtype: DnsType, qclass: DnsClass) -> DnsQuestion {
+        DnsQuestion { qname, qtype, qclass }
+    }
+
+    fn write(&self, pb: &mut PacketBuffer) {
+        pb.write_qname(&self.qname);
+        pb.write_u16(self.qtype.to_u16());
+        pb.write_u16(self.qclass.to_u8() as u16);
+    }
+
+    fn read(pb: &mut PacketBuffer) -> Result<DnsQuestion, String> {
+        let qname = pb.read_name()?;
+        let qtype = DnsType::from_u16(pb.read_u16()?);
+        let qclass = DnsClass::from_u8(pb.read_u16()? as u8);
+        Ok(DnsQuestion { qname, qtype, qclass })
+    }
+}
+
+/* RFC 6891 EDNS0 OPT pseudo-RR, attached to the additional section of an
+ * outgoing query to advertise our UDP payload size and (optionally)
+ * request DNSSEC data via the DO bit. */
+pub(crate) struct EdnsOpt {
+    udp_payload_size: u16,
+    dnssec_ok: bool,
+}
+
+impl EdnsOpt {
+    pub(crate) fn new(udp_payload_size: u16, dnssec_ok: bool) -> EdnsOpt {
+        EdnsOpt { udp_payload_size, dnssec_ok }
+    }
+
+    fn write(&self, pb: &mut PacketBuffer) {
+        pb.write_u8(0); // NAME = root
+        pb.write_u16(DnsType::Opt.to_u16());
+        pb.write_u16(self.udp_payload_size); // CLASS is repurposed as the payload size
+        // TTL is repurposed as extended RCODE (8) | version (8) | flags (16); we
+        // only ever send extended RCODE 0, version 0, and the DO bit.
+        let ttl: u32 = if self.dnssec_ok { 0x8000 } else { 0 };
+        pb.write_u32(ttl);
+        pb.write_u16(0); // RDLENGTH, no options
+    }
+}
+
+/* Decoded RDATA for the record types we know how to interpret. Anything
+ * else is kept as-is so it can still be displayed (as opaque bytes). */
+#[derive(Clone)]
+pub(crate) enum RData {
+    A(Ipv4Addr),
+    Aaaa(Ipv6Addr),
+    Cname(String),
+    Ns(String),
+    Mx { preference: u16, exchange: String },
+    Soa {
+        mname: String,
+        rname: String,
+        serial: u32,
+        refresh: u32,
+        retry: u32,
+        expire: u32,
+        minimum: u32,
+    },
+    Txt(String),
+    /* An EDNS0 OPT pseudo-record, as seen in a response's additional
+     * section; see RFC 6891. */
+    Opt { udp_payload_size: u16, version: u8, dnssec_ok: bool },
+    Rrsig {
+        type_covered: DnsType,
+        algorithm: u8,
+        labels: u8,
+        original_ttl: u32,
+        expiration: u32,
+        inception: u32,
+        key_tag: u16,
+        signer_name: String,
+        signature: Vec<u8>,
+    },
+    Dnskey { flags: u16, protocol: u8, algorithm: u8, public_key: Vec<u8> },
+    Unknown(Vec<u8>),
+}
+
+fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+impl std::fmt::Display for RData {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            RData::A(addr) => write!(f, "{}", addr),
+            RData::Aaaa(addr) => write!(f, "{}", addr),
+            RData::Cname(name) => write!(f, "{}", name),
+            RData::Ns(name) => write!(f, "{}", name),
+            RData::Mx { preference, exchange } => write!(f, "{} {}", preference, exchange),
+            RData::Soa { mname, rname, serial, refresh, retry, expire, minimum } => write!(
+                f,
+                "{} {} {} {} {} {} {}",
+                mname, rname, serial, refresh, retry, expire, minimum
+            ),
+            RData::Txt(s) => write!(f, "\"{}\"", s),
+            RData::Opt { udp_payload_size, version, dnssec_ok } => write!(
+                f,
+                "udp: {}, version: {}, flags:{}",
+                udp_payload_size, version, if *dnssec_ok { " do" } else { "" }
+            ),
+            RData::Rrsig {
+                type_covered, algorithm, labels, original_ttl, expiration, inception, key_tag, signer_name, signature,
+            } => write!(
+                f,
+                "{} {} {} {} {} {} {} {} {}",
+                type_covered.dig_name(), algorithm, labels, original_ttl, expiration, inception, key_tag,
+                signer_name, hex(signature)
+            ),
+            RData::Dnskey { flags, protocol, algorithm, public_key } => {
+                write!(f, "{} {} {} {}", flags, protocol, algorithm, hex(public_key))
+            }
+            RData::Unknown(bytes) => {
+                write!(f, "\\# {}", bytes.len())?;
+                for b in bytes {
+                    write!(f, " {:02x}", b)?;
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+#[derive(Clone)]
+pub(crate) struct DnsRecord {
+    pub(crate) name: String,
+    pub(crate) rtype: DnsType,
+    pub(crate) rclass: DnsClass,
+    pub(crate) ttl: u32,
+    pub(crate) rdata: RData,
+}
+
+impl DnsRecord {
+    fn read(pb: &mut PacketBuffer) -> Result<DnsRecord, String> {
+        let name = pb.read_name()?;
+        let rtype = DnsType::from_u16(pb.read_u16()?);
+
+        /* OPT repurposes the CLASS field as the UDP payload size and the
+         * TTL field as extended RCODE/version/flags rather than an actual
+         * class and TTL, so it needs its own decoding path. */
+        if matches!(rtype, DnsType::Opt) {
+            let udp_payload_size = pb.read_u16()?;
+            let ttl = pb.read_u32()?;
+            let rdlength = pb.read_u16()?;
+            pb.seek(pb.pos + rdlength as usize);
+
+            let version = ((ttl >> 16) & 0xFF) as u8;
+            let dnssec_ok = ttl & 0x8000 != 0;
+
+            return Ok(DnsRecord {
+                name,
+                rtype,
+                rclass: DnsClass::Internet,
+                ttl,
+                rdata: RData::Opt { udp_payload_size, version, dnssec_ok },
+            });
+        }
+
+        let rclass = DnsClass::from_u8(pb.read_u16()? as u8);
+        let ttl = pb.read_u32()?;
+        let rdlength = pb.read_u16()?;
+        let rdata_start = pb.pos;
+
+        let rdata = match rtype {
+            DnsType::Address => {
+                let a = pb.read_u8()?;
+                let b = pb.read_u8()?;
+                let c = pb.read_u8()?;
+                let d = pb.read_u8()?;
+                RData::A(Ipv4Addr::new(a, b, c, d))
+            }
+            DnsType::Aaaa => {
+                let mut octets = [0u8; 16];
+                for octet in &mut octets {
+                    *octet = pb.read_u8()?;
+                }
+                RData::Aaaa(Ipv6Addr::from(octets))
+            }
+            DnsType::CName => RData::Cname(pb.read_name()?),
+            DnsType::NameServer => RData::Ns(pb.read_name()?),
+            DnsType::MailExchange => {
+                let preference = pb.read_u16()?;
+                let exchange = pb.read_name()?;
+                RData::Mx { preference, exchange }
+            }
+            DnsType::StartOfAuthority => {
+                let mname = pb.read_name()?;
+                let rname = pb.read_name()?;
+                let serial = pb.read_u32()?;
+                let refresh = pb.read_u32()?;
+                let retry = pb.read_u32()?;
+                let expire = pb.read_u32()?;
+                let minimum = pb.read_u32()?;
+                RData::Soa { mname, rname, serial, refresh, retry, expire, minimum }
+            }
+            DnsType::Text => {
+                let len = pb.read_u8()? as usize;
+                let bytes = pb.read_bytes(len)?;
+                RData::Txt(String::from_utf8_lossy(&bytes).to_string())
+            }
+            DnsType::Rrsig => {
+                let type_covered = DnsType::from_u16(pb.read_u16()?);
+                let algorithm = pb.read_u8()?;
+                let labels = pb.read_u8()?;
+                let original_ttl = pb.read_u32()?;
+                let expiration = pb.read_u32()?;
+                let inception = pb.read_u32()?;
+                let key_tag = pb.read_u16()?;
+                let signer_name = pb.read_name()?;
+                let consumed = pb.pos - rdata_start;
+                let remaining = (rdlength as usize)
+                    .checked_sub(consumed)
+                    .ok_or("RRSIG record's RDLENGTH is too small for its fixed-size fields")?;
+                let signature = pb.read_bytes(remaining)?;
+                RData::Rrsig {
+                    type_covered, algorithm, labels, original_ttl, expiration, inception, key_tag,
+                    signer_name, signature,
+                }
+            }
+            DnsType::Dnskey => {
+                let flags = pb.read_u16()?;
+                let protocol = pb.read_u8()?;
+                let algorithm = pb.read_u8()?;
+                let remaining = (rdlength as usize)
+                    .checked_sub(4)
+                    .ok_or("DNSKEY record's RDLENGTH is too small for its fixed-size fields")?;
+                let public_key = pb.read_bytes(remaining)?;
+                RData::Dnskey { flags, protocol, algorithm, public_key }
+            }
+            _ => RData::Unknown(pb.read_bytes(rdlength as usize)?),
+        };
+
+        /* A name inside RDATA may itself use compression, so the amount we
+         * actually consumed can legitimately differ from RDLENGTH; always
+         * resync to the declared boundary before reading the next record. */
+        pb.seek(rdata_start + rdlength as usize);
+
+        Ok(DnsRecord { name, rtype, rclass, ttl, rdata })
+    }
+}
+
+pub(crate) struct DnsMessage {
+    pub(crate) header: DnsHeader,
+    pub(crate) questions: Vec<DnsQuestion>,
+    pub(crate) answers: Vec<DnsRecord>,
+    pub(crate) authorities: Vec<DnsRecord>,
+    pub(crate) additionals: Vec<DnsRecord>,
+    /* EDNS0 OPT pseudo-record to attach to an outgoing query, if any. */
+    pub(crate) edns: Option<EdnsOpt>,
+}
+
+impl DnsMessage {
+    pub(crate) fn new(header: DnsHeader) -> DnsMessage {
+        DnsMessage {
+            header,
+            questions: Vec::new(),
+            answers: Vec::new(),
+            authorities: Vec::new(),
+            additionals: Vec::new(),
+            edns: None,
+        }
+    }
+
+    pub(crate) fn to_bytes(&self) -> Vec<u8> {
+        let mut pb = PacketBuffer::new();
+        let arcount = self.additionals.len() as u16 + if self.edns.is_some() { 1 } else { 0 };
+
+        pb.write_u16(self.header.id);
+        /* DnsHeader's accessors number the bits of `cfg` in the RFC 1035
+         * diagram's column order (QR is bit 0, RCODE is bits 12-15), which
+         * is the wire value with its bits reversed. */
+        pb.write_u16(self.header.cfg.reverse_bits());
+        pb.write_u16(self.questions.len() as u16);
+        pb.write_u16(self.answers.len() as u16);
+        pb.write_u16(self.authorities.len() as u16);
+        pb.write_u16(arcount);
+
+        for question in &self.questions {
+            question.write(&mut pb);
+        }
+
+        if let Some(opt) = &self.edns {
+            opt.write(&mut pb);
+        }
+
+        pb.buf
+    }
+
+    /* A reply is untrusted input (a spoofed or truncated UDP datagram), so
+     * a malformed one is reported as an error instead of panicking. */
+    pub(crate) fn from_bytes(buf: Vec<u8>) -> Result<DnsMessage, String> {
+        let mut pb = PacketBuffer::from_bytes(buf);
+
+        let mut header = DnsHeader::new();
+        header.id = pb.read_u16()?;
+        header.cfg = pb.read_u16()?.reverse_bits(); // see `to_bytes`
+        header.qdcount = pb.read_u16()?;
+        header.ancount = pb.read_u16()?;
+        header.nscount = pb.read_u16()?;
+        header.arcount = pb.read_u16()?;
+
+        let mut message = DnsMessage::new(header);
+
+        for _ in 0..message.header.qdcount {
+            message.questions.push(DnsQuestion::read(&mut pb)?);
+        }
+        for _ in 0..message.header.ancount {
+            message.answers.push(DnsRecord::read(&mut pb)?);
+        }
+        for _ in 0..message.header.nscount {
+            message.authorities.push(DnsRecord::read(&mut pb)?);
+        }
+        for _ in 0..message.header.arcount {
+            message.additionals.push(DnsRecord::read(&mut pb)?);
+        }
+
+        Ok(message)
+    }
+
+    /* Pretty-prints the message roughly the way `dig` lays out a response. */
+    pub(crate) fn print(&self) {
+        println!(";; status: {}", self.header.rcode_name());
+        println!();
+
+        println!(";; QUESTION SECTION:");
+        for q in &self.questions {
+            println!(";{}\t\t{}\t{}", q.qname, q.qclass.dig_name(), q.qtype.dig_name());
+        }
+        println!();
+
+        if let Some(opt) = self.additionals.iter().find(|r| matches!(r.rtype, DnsType::Opt)) {
+            println!(";; OPT PSEUDOSECTION:");
+            println!("; EDNS: {}", opt.rdata);
+            println!();
+        }
+
+        Self::print_section("ANSWER", &self.answers);
+        Self::print_section("AUTHORITY", &self.authorities);
+        Self::print_section(
+            "ADDITIONAL",
+            self.additionals.iter().filter(|r| !matches!(r.rtype, DnsType::Opt)),
+        );
+    }
+
+    pub(crate) fn print_section<'a>(title: &str, records: impl IntoIterator<Item = &'a DnsRecord>) {
+        let mut records = records.into_iter().peekable();
+        if records.peek().is_none() {
+            return;
+        }
+
+        println!(";; {} SECTION:", title);
+        for r in records {
+            println!(
+                "{}\t{}\t{}\t{}\t{}",
+                r.name,
+                r.ttl,
+                r.rclass.dig_name(),
+                r.rtype.dig_name(),
+                r.rdata
+            );
+        }
+        println!();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn read_name_follows_a_compression_pointer() {
+        let mut buf = vec![7];
+        buf.extend_from_slice(b"example");
+        buf.push(3);
+        buf.extend_from_slice(b"com");
+        buf.push(0);
+        buf.extend_from_slice(&[0xC0, 0x00]); // pointer back to offset 0
+
+        let mut pb = PacketBuffer::from_bytes(buf);
+        assert_eq!(pb.read_name().unwrap(), "example.com");
+        assert_eq!(pb.read_name().unwrap(), "example.com");
+    }
+
+    #[test]
+    fn read_name_rejects_a_pointer_loop() {
+        // Two mutually-referencing pointers would otherwise loop forever.
+        let buf = vec![0xC0, 0x02, 0xC0, 0x00];
+        let mut pb = PacketBuffer::from_bytes(buf);
+        assert!(pb.read_name().is_err());
+    }
+
+    #[test]
+    fn read_name_on_truncated_buffer_is_an_error_not_a_panic() {
+        let mut pb = PacketBuffer::from_bytes(Vec::new());
+        assert!(pb.read_name().is_err());
+    }
+
+    #[test]
+    fn read_name_rejects_a_pointer_past_the_end() {
+        let buf = vec![0xC0, 0xFF];
+        let mut pb = PacketBuffer::from_bytes(buf);
+        assert!(pb.read_name().is_err());
+    }
+
+    #[test]
+    fn decodes_an_a_record() {
+        let mut buf = vec![0]; // root name
+        buf.extend_from_slice(&1u16.to_be_bytes()); // TYPE A
+        buf.extend_from_slice(&1u16.to_be_bytes()); // CLASS IN
+        buf.extend_from_slice(&300u32.to_be_bytes()); // TTL
+        buf.extend_from_slice(&4u16.to_be_bytes()); // RDLENGTH
+        buf.extend_from_slice(&[127, 0, 0, 1]);
+
+        let mut pb = PacketBuffer::from_bytes(buf);
+        let record = DnsRecord::read(&mut pb).unwrap();
+        assert!(matches!(record.rdata, RData::A(addr) if addr == Ipv4Addr::new(127, 0, 0, 1)));
+    }
+
+    #[test]
+    fn decodes_an_aaaa_record() {
+        let mut buf = vec![0]; // root name
+        buf.extend_from_slice(&28u16.to_be_bytes()); // TYPE AAAA
+        buf.extend_from_slice(&1u16.to_be_bytes()); // CLASS IN
+        buf.extend_from_slice(&300u32.to_be_bytes()); // TTL
+        buf.extend_from_slice(&16u16.to_be_bytes()); // RDLENGTH
+        let addr = Ipv6Addr::new(0x2001, 0xdb8, 0, 0, 0, 0, 0, 1);
+        buf.extend_from_slice(&addr.octets());
+
+        let mut pb = PacketBuffer::from_bytes(buf);
+        let record = DnsRecord::read(&mut pb).unwrap();
+        assert!(matches!(record.rdata, RData::Aaaa(got) if got == addr));
+    }
+
+    #[test]
+    fn rrsig_with_undersized_rdlength_is_a_decode_error_not_a_panic() {
+        let mut buf = vec![0]; // owner name: root
+        buf.extend_from_slice(&46u16.to_be_bytes()); // TYPE RRSIG
+        buf.extend_from_slice(&1u16.to_be_bytes()); // CLASS IN
+        buf.extend_from_slice(&60u32.to_be_bytes()); // TTL
+        buf.extend_from_slice(&2u16.to_be_bytes()); // RDLENGTH, far too small
+        buf.extend_from_slice(&[0, 0]);
+
+        let mut pb = PacketBuffer::from_bytes(buf);
+        assert!(DnsRecord::read(&mut pb).is_err());
+    }
+
+    #[test]
+    fn edns_opt_round_trips_the_payload_size_and_do_bit() {
+        for dnssec_ok in [true, false] {
+            let mut pb = PacketBuffer::new();
+            EdnsOpt::new(4096, dnssec_ok).write(&mut pb);
+
+            let mut read_pb = PacketBuffer::from_bytes(pb.buf);
+            let record = DnsRecord::read(&mut read_pb).unwrap();
+            assert!(matches!(
+                record.rdata,
+                RData::Opt { udp_payload_size: 4096, version: 0, dnssec_ok: got } if got == dnssec_ok
+            ));
+        }
+    }
+
+    #[test]
+    fn decodes_a_dnskey_record() {
+        let mut buf = vec![0]; // owner name: root
+        buf.extend_from_slice(&48u16.to_be_bytes()); // TYPE DNSKEY
+        buf.extend_from_slice(&1u16.to_be_bytes()); // CLASS IN
+        buf.extend_from_slice(&3600u32.to_be_bytes()); // TTL
+        let mut rdata = Vec::new();
+        rdata.extend_from_slice(&257u16.to_be_bytes()); // flags: zone key + SEP
+        rdata.push(3); // protocol
+        rdata.push(8); // algorithm: RSA/SHA-256
+        rdata.extend_from_slice(&[0xAA, 0xBB, 0xCC, 0xDD]); // public key
+        buf.extend_from_slice(&(rdata.len() as u16).to_be_bytes());
+        buf.extend_from_slice(&rdata);
+
+        let mut pb = PacketBuffer::from_bytes(buf);
+        let record = DnsRecord::read(&mut pb).unwrap();
+        assert!(matches!(
+            record.rdata,
+            RData::Dnskey { flags: 257, protocol: 3, algorithm: 8, ref public_key }
+                if public_key == &[0xAA, 0xBB, 0xCC, 0xDD]
+        ));
+    }
+
+    #[test]
+    fn decodes_a_cname_record_pointing_at_a_compressed_name() {
+        let mut buf = vec![7]; // owner name: example.com, at offset 0
+        buf.extend_from_slice(b"example");
+        buf.push(3);
+        buf.extend_from_slice(b"com");
+        buf.push(0);
+        buf.extend_from_slice(&5u16.to_be_bytes()); // TYPE CNAME
+        buf.extend_from_slice(&1u16.to_be_bytes()); // CLASS IN
+        buf.extend_from_slice(&60u32.to_be_bytes()); // TTL
+        let rdata_offset = buf.len() + 2;
+        let mut rdata = Vec::new();
+        rdata.push(3);
+        rdata.extend_from_slice(b"www");
+        rdata.extend_from_slice(&[0xC0, 0x00]); // pointer back to the owner name
+        buf.extend_from_slice(&(rdata.len() as u16).to_be_bytes());
+        buf.extend_from_slice(&rdata);
+        assert_eq!(buf.len(), rdata_offset + rdata.len());
+
+        let mut pb = PacketBuffer::from_bytes(buf);
+        let record = DnsRecord::read(&mut pb).unwrap();
+        assert!(matches!(record.rdata, RData::Cname(name) if name == "www.example.com"));
+    }
+}
@@ -0,0 +1,173 @@
+/* Parsing of /etc/resolv.conf, see `man 5 resolv.conf`. */
+
+use std::net::Ipv4Addr;
+use std::str::FromStr;
+
+/* Defaults mirror what glibc's resolver falls back to when resolv.conf is
+ * missing or incomplete. */
+const DEFAULT_NDOTS: u32 = 1;
+const DEFAULT_TIMEOUT: u32 = 5;
+const DEFAULT_ATTEMPTS: u32 = 2;
+
+pub(crate) struct ResolvConf {
+    /* Ordered list of nameservers to try in sequence on failure/timeout. */
+    pub(crate) nameservers: Vec<Ipv4Addr>,
+    /* Search suffixes used to qualify a bare hostname. */
+    pub(crate) search: Vec<String>,
+    pub(crate) ndots: u32,
+    pub(crate) timeout: u32,
+    pub(crate) attempts: u32,
+}
+
+impl ResolvConf {
+    fn empty() -> ResolvConf {
+        ResolvConf {
+            nameservers: Vec::new(),
+            search: Vec::new(),
+            ndots: DEFAULT_NDOTS,
+            timeout: DEFAULT_TIMEOUT,
+            attempts: DEFAULT_ATTEMPTS,
+        }
+    }
+
+    /* A single nameserver on the loopback interface, used when resolv.conf
+     * cannot be read at all. */
+    fn default() -> ResolvConf {
+        let mut conf = ResolvConf::empty();
+        conf.nameservers.push(Ipv4Addr::new(127, 0, 0, 1));
+        conf
+    }
+
+    pub(crate) fn load(path: &str) -> ResolvConf {
+        match std::fs::read_to_string(path) {
+            Ok(contents) => ResolvConf::parse(&contents),
+            Err(e) => {
+                println!("W: could not read {}: {}; falling back to {}", path, e, Ipv4Addr::new(127, 0, 0, 1));
+                ResolvConf::default()
+            }
+        }
+    }
+
+    fn parse(contents: &str) -> ResolvConf {
+        let mut conf = ResolvConf::empty();
+
+        for line in contents.lines() {
+            let mut fields = line.split_whitespace();
+            match fields.next() {
+                Some("nameserver") => match fields.next().and_then(|s| Ipv4Addr::from_str(s).ok()) {
+                    Some(addr) => conf.nameservers.push(addr),
+                    None => println!("W: ignoring malformed nameserver line: {}", line),
+                },
+                Some("search") => conf.search = fields.map(String::from).collect(),
+                Some("domain") => {
+                    if let Some(domain) = fields.next() {
+                        conf.search = vec![domain.to_string()];
+                    }
+                }
+                Some("options") => {
+                    for option in fields {
+                        if let Some(v) = option.strip_prefix("ndots:") {
+                            conf.ndots = v.parse().unwrap_or(conf.ndots);
+                        } else if let Some(v) = option.strip_prefix("timeout:") {
+                            conf.timeout = v.parse().unwrap_or(conf.timeout);
+                        } else if let Some(v) = option.strip_prefix("attempts:") {
+                            conf.attempts = v.parse().unwrap_or(conf.attempts);
+                        }
+                    }
+                }
+                _ => {}
+            }
+        }
+
+        if conf.nameservers.is_empty() {
+            println!("W: no usable nameserver entries in resolv.conf; falling back to {}", Ipv4Addr::new(127, 0, 0, 1));
+            return ResolvConf::default();
+        }
+
+        conf
+    }
+
+    /* Qualifies a bare hostname against the search list, following the
+     * same `ndots` rule glibc's resolver uses: a name with at least
+     * `ndots` dots is tried as-is first, otherwise the search suffixes are
+     * tried first and the bare name last. */
+    pub(crate) fn qualify(&self, name: &str) -> Vec<String> {
+        let absolute = name.trim_end_matches('.').to_string();
+
+        if self.search.is_empty() {
+            return vec![absolute];
+        }
+
+        let dots = absolute.matches('.').count() as u32;
+        let suffixed = self.search.iter().map(|suffix| format!("{}.{}", absolute, suffix));
+
+        if dots >= self.ndots {
+            std::iter::once(absolute.clone()).chain(suffixed).collect()
+        } else {
+            suffixed.chain(std::iter::once(absolute.clone())).collect()
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_nameservers_search_and_options() {
+        let conf = ResolvConf::parse(
+            "nameserver 192.0.2.1\nnameserver 192.0.2.2\nsearch example.com corp.example.com\noptions ndots:2 timeout:3 attempts:1\n",
+        );
+
+        assert_eq!(conf.nameservers, vec![Ipv4Addr::new(192, 0, 2, 1), Ipv4Addr::new(192, 0, 2, 2)]);
+        assert_eq!(conf.search, vec!["example.com".to_string(), "corp.example.com".to_string()]);
+        assert_eq!(conf.ndots, 2);
+        assert_eq!(conf.timeout, 3);
+        assert_eq!(conf.attempts, 1);
+    }
+
+    #[test]
+    fn domain_directive_is_a_single_entry_search_list() {
+        let conf = ResolvConf::parse("nameserver 192.0.2.1\ndomain example.com\n");
+        assert_eq!(conf.search, vec!["example.com".to_string()]);
+    }
+
+    #[test]
+    fn malformed_nameserver_line_is_ignored_not_fatal() {
+        let conf = ResolvConf::parse("nameserver not-an-ip\nnameserver 192.0.2.1\n");
+        assert_eq!(conf.nameservers, vec![Ipv4Addr::new(192, 0, 2, 1)]);
+    }
+
+    #[test]
+    fn empty_nameserver_list_falls_back_to_loopback() {
+        let conf = ResolvConf::parse("search example.com\n");
+        assert_eq!(conf.nameservers, vec![Ipv4Addr::new(127, 0, 0, 1)]);
+    }
+
+    #[test]
+    fn qualify_without_search_list_returns_the_bare_name() {
+        let conf = ResolvConf::empty();
+        assert_eq!(conf.qualify("foo"), vec!["foo".to_string()]);
+    }
+
+    #[test]
+    fn qualify_tries_search_suffixes_before_the_bare_name_below_ndots() {
+        let mut conf = ResolvConf::empty();
+        conf.search = vec!["example.com".to_string(), "corp.example.com".to_string()];
+        conf.ndots = 1;
+
+        assert_eq!(
+            conf.qualify("foo"),
+            vec!["foo.example.com".to_string(), "foo.corp.example.com".to_string(), "foo".to_string()]
+        );
+    }
+
+    #[test]
+    fn qualify_tries_the_name_as_is_first_at_or_above_ndots() {
+        let mut conf = ResolvConf::empty();
+        conf.search = vec!["example.com".to_string()];
+        conf.ndots = 1;
+
+        assert_eq!(conf.qualify("foo.bar"), vec!["foo.bar".to_string(), "foo.bar.example.com".to_string()]);
+    }
+}
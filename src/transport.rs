@@ -0,0 +1,59 @@
+/* Transport-agnostic delivery of a wire-format DNS message to a resolver. */
+
+use std::io::{Read, Write};
+use std::net::{Ipv4Addr, TcpStream, UdpSocket};
+use std::time::Duration;
+
+use socket::{htons, ntohs};
+
+pub(crate) enum Transport {
+    Udp,
+    Tcp,
+}
+
+impl Transport {
+    /* Sends `request` to `resolver:port` and returns the raw reply bytes. */
+    pub(crate) fn send_query(
+        &self,
+        resolver: Ipv4Addr,
+        port: u16,
+        timeout: Duration,
+        request: &[u8],
+    ) -> std::io::Result<Vec<u8>> {
+        match self {
+            Transport::Udp => send_udp(resolver, port, timeout, request),
+            Transport::Tcp => send_tcp(resolver, port, timeout, request),
+        }
+    }
+}
+
+fn send_udp(resolver: Ipv4Addr, port: u16, timeout: Duration, request: &[u8]) -> std::io::Result<Vec<u8>> {
+    let socket = UdpSocket::bind("127.0.0.1:0")?;
+    socket.set_read_timeout(Some(timeout))?;
+    socket.send_to(request, (resolver, port))?;
+
+    let mut buf = [0u8; 4096];
+    let (len, _) = socket.recv_from(&mut buf)?;
+    Ok(buf[..len].to_vec())
+}
+
+/* RFC 1035 section 4.2.2: TCP messages are prefixed with a 2-byte
+ * big-endian length field. */
+fn send_tcp(resolver: Ipv4Addr, port: u16, timeout: Duration, request: &[u8]) -> std::io::Result<Vec<u8>> {
+    let mut stream = TcpStream::connect((resolver, port))?;
+    stream.set_read_timeout(Some(timeout))?;
+    stream.set_write_timeout(Some(timeout))?;
+
+    let mut framed = Vec::with_capacity(2 + request.len());
+    framed.extend_from_slice(&htons(request.len() as u16).to_ne_bytes());
+    framed.extend_from_slice(request);
+    stream.write_all(&framed)?;
+
+    let mut len_buf = [0u8; 2];
+    stream.read_exact(&mut len_buf)?;
+    let len = ntohs(u16::from_ne_bytes(len_buf)) as usize;
+
+    let mut response = vec![0u8; len];
+    stream.read_exact(&mut response)?;
+    Ok(response)
+}